@@ -0,0 +1,4 @@
+//! Mapping of validated OIDC claims onto the fields of a signed SSH
+//! certificate (principals, critical options, extensions, validity window).
+
+pub(crate) mod policy;
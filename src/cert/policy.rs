@@ -0,0 +1,223 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+/// The fields of a to-be-signed SSH certificate that are derived from OIDC
+/// claims: the principal list plus the certificate's critical options and
+/// extensions (see `ssh-keygen -h` for what these mean on the SSH side) and
+/// the validity window the certificate should carry.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct CertFields {
+    pub principals: Vec<String>,
+    pub critical_options: HashMap<String, String>,
+    pub extensions: HashMap<String, String>,
+    pub valid_before: u64,
+}
+
+/// Derives [`CertFields`] from the claims of a validated OIDC token.
+///
+/// Implementations receive the claims as raw JSON so they can pull arbitrary
+/// claim names without requiring every caller's claims struct to declare
+/// them up front.
+pub(crate) trait CertPolicy {
+    fn cert_fields(&self, claims: &Value) -> CertFields;
+}
+
+/// A `claim -> value` extension or critical option that is only added when
+/// the named claim is present and, if `when` is set, equal to it.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ClaimToggle {
+    /// Name of the claim to inspect.
+    claim: String,
+    /// Required value for the toggle to fire. If unset, the toggle fires
+    /// whenever the claim is present, whatever its value (`false` included).
+    #[serde(default)]
+    when: Option<String>,
+    /// Name of the resulting critical option or extension, e.g.
+    /// `"permit-pty"` or `"force-command"`.
+    option: String,
+    /// Value to put into the resulting option. For empty-valued SSH
+    /// extensions like `permit-pty`, leave this as the empty string. For
+    /// `force-command`/`source-address`, set a template containing `{claim}`
+    /// to substitute the claim's own value.
+    #[serde(default)]
+    value: String,
+}
+
+impl ClaimToggle {
+    fn resolve(&self, claims: &Value) -> Option<(String, String)> {
+        let claim_value = claims.get(&self.claim)?;
+        let claim_str = claim_value.as_str().map(str::to_owned).unwrap_or_else(|| claim_value.to_string());
+
+        if let Some(want) = &self.when {
+            if &claim_str != want {
+                return None;
+            }
+        }
+
+        let value = self.value.replace("{claim}", &claim_str);
+        Some((self.option.clone(), value))
+    }
+}
+
+/// A declarative, serde-configurable [`CertPolicy`] that lets operators state
+/// "claim X -> principal/option Y" instead of editing signing code.
+///
+/// # Example configuration
+/// ```toml
+/// principal_claims = ["preferred_username", "email"]
+/// group_claim = "groups"
+/// group_principal_prefix = "group-"
+/// validity_seconds = 28800
+///
+/// [[critical_options]]
+/// claim = "ssh_source_address"
+/// option = "source-address"
+/// value = "{claim}"
+///
+/// [[extensions]]
+/// claim = "ssh_permit_pty"
+/// when = "true"
+/// option = "permit-pty"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct DefaultCertPolicy {
+    /// Claims whose string values are each added as a principal, in this
+    /// order, when present.
+    principal_claims: Vec<String>,
+    /// Claim holding an array of group names, each added as an extra
+    /// principal (after prefixing), if present.
+    #[serde(default)]
+    group_claim: Option<String>,
+    #[serde(default)]
+    group_principal_prefix: String,
+    /// Critical options to add, gated on claim presence/value.
+    #[serde(default)]
+    critical_options: Vec<ClaimToggle>,
+    /// Extensions to add, gated on claim presence/value.
+    #[serde(default)]
+    extensions: Vec<ClaimToggle>,
+    /// Certificate validity window, capped so it never outlives the token's
+    /// own `exp`. Falls back to this value if `exp` is absent.
+    #[serde(default = "DefaultCertPolicy::default_validity_seconds")]
+    validity_seconds: u64,
+}
+
+impl DefaultCertPolicy {
+    fn default_validity_seconds() -> u64 {
+        3600
+    }
+}
+
+impl CertPolicy for DefaultCertPolicy {
+    fn cert_fields(&self, claims: &Value) -> CertFields {
+        let mut principals: Vec<String> = self
+            .principal_claims
+            .iter()
+            .filter_map(|claim| claims.get(claim).and_then(Value::as_str))
+            .map(str::to_owned)
+            .collect();
+
+        if let Some(group_claim) = &self.group_claim {
+            if let Some(groups) = claims.get(group_claim).and_then(Value::as_array) {
+                principals.extend(
+                    groups
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(|group| format!("{}{}", self.group_principal_prefix, group)),
+                );
+            }
+        }
+
+        // `principal_claims` and the group-derived principals are
+        // concatenated above, so duplicates (e.g. a claim value that also
+        // shows up, possibly re-prefixed, via the group claim) aren't
+        // necessarily adjacent — `Vec::dedup` alone would miss those.
+        let mut seen = HashSet::with_capacity(principals.len());
+        principals.retain(|principal| seen.insert(principal.clone()));
+
+        let critical_options = self
+            .critical_options
+            .iter()
+            .filter_map(|toggle| toggle.resolve(claims))
+            .collect();
+        let extensions = self
+            .extensions
+            .iter()
+            .filter_map(|toggle| toggle.resolve(claims))
+            .collect();
+
+        let token_exp = claims.get("exp").and_then(Value::as_u64);
+        let now = unix_now();
+        let valid_before = match token_exp {
+            // Never let the certificate outlive the token's own session.
+            Some(exp) => exp.min(now + self.validity_seconds),
+            None => now + self.validity_seconds,
+        };
+
+        CertFields {
+            principals,
+            critical_options,
+            extensions,
+            valid_before,
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("system clock before unix epoch")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn policy() -> DefaultCertPolicy {
+        DefaultCertPolicy {
+            principal_claims: vec!["preferred_username".to_string()],
+            group_claim: Some("groups".to_string()),
+            group_principal_prefix: String::new(),
+            critical_options: vec![],
+            extensions: vec![],
+            validity_seconds: 3600,
+        }
+    }
+
+    #[test]
+    fn dedupes_principals_shared_by_claim_and_group_sources() {
+        let claims = json!({
+            "preferred_username": "alice",
+            "groups": ["alice", "ssh-admins"],
+        });
+
+        let fields = policy().cert_fields(&claims);
+
+        assert_eq!(fields.principals, vec!["alice".to_string(), "ssh-admins".to_string()]);
+    }
+
+    #[test]
+    fn validity_is_capped_at_token_exp() {
+        let exp = unix_now() + 60;
+        let claims = json!({ "preferred_username": "alice", "exp": exp });
+
+        let fields = policy().cert_fields(&claims);
+
+        assert_eq!(fields.valid_before, exp);
+    }
+
+    #[test]
+    fn validity_falls_back_to_validity_seconds_without_exp() {
+        let claims = json!({ "preferred_username": "alice" });
+
+        let fields = policy().cert_fields(&claims);
+
+        assert!(fields.valid_before <= unix_now() + policy().validity_seconds);
+        assert!(fields.valid_before >= unix_now());
+    }
+}
@@ -0,0 +1,138 @@
+//! RFC 8628 Device Authorization Grant client.
+//!
+//! Lets a headless caller (an SSH session, a CLI, a script) obtain an access
+//! token without a browser redirect, so it can be handed to this crate's
+//! `/sign` endpoint like any other bearer token.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum DeviceFlowError {
+    #[error("request to device authorization or token endpoint failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("provider denied the authorization request")]
+    AccessDenied,
+    #[error("device code expired before the user completed authorization")]
+    ExpiredToken,
+    #[error("provider returned an unexpected error: {0}")]
+    Provider(String),
+}
+
+/// Response to the initial device authorization request (RFC 8628 §3.2).
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct DeviceAuthorization {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    #[serde(default)]
+    pub verification_uri_complete: Option<String>,
+    pub expires_in: u64,
+    #[serde(default = "DeviceAuthorization::default_interval")]
+    pub interval: u64,
+}
+
+impl DeviceAuthorization {
+    fn default_interval() -> u64 {
+        5
+    }
+}
+
+/// Access/ID tokens returned once the user has completed authorization.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct DeviceTokens {
+    pub access_token: String,
+    #[serde(default)]
+    pub id_token: Option<String>,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    pub expires_in: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+enum TokenErrorBody {
+    AuthorizationPending,
+    SlowDown,
+    AccessDenied,
+    ExpiredToken,
+    #[serde(other)]
+    Other,
+}
+
+const GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Starts a device authorization flow against `device_authorization_endpoint`.
+///
+/// The returned [`DeviceAuthorization`] carries the `user_code` and
+/// `verification_uri` the caller should display to the user before calling
+/// [`poll_for_tokens`].
+pub(crate) async fn start(
+    http: &reqwest::Client,
+    device_authorization_endpoint: &str,
+    client_id: &str,
+    scope: &str,
+) -> Result<DeviceAuthorization, DeviceFlowError> {
+    let response = http
+        .post(device_authorization_endpoint)
+        .form(&[("client_id", client_id), ("scope", scope)])
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<DeviceAuthorization>()
+        .await?;
+
+    Ok(response)
+}
+
+/// Polls `token_endpoint` with the device code until the user completes
+/// authorization, the provider denies/expires the request, or a non-device
+/// error is returned.
+///
+/// Honors `authorization_pending` (keep polling at the current interval) and
+/// `slow_down` (increase the interval by 5 seconds, per RFC 8628 §3.5).
+pub(crate) async fn poll_for_tokens(
+    http: &reqwest::Client,
+    token_endpoint: &str,
+    client_id: &str,
+    authorization: &DeviceAuthorization,
+) -> Result<DeviceTokens, DeviceFlowError> {
+    let mut interval = Duration::from_secs(authorization.interval);
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(authorization.expires_in);
+
+    loop {
+        tokio::time::sleep(interval).await;
+        if tokio::time::Instant::now() >= deadline {
+            return Err(DeviceFlowError::ExpiredToken);
+        }
+
+        let response = http
+            .post(token_endpoint)
+            .form(&[
+                ("grant_type", GRANT_TYPE),
+                ("device_code", &authorization.device_code),
+                ("client_id", client_id),
+            ])
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            return Ok(response.json::<DeviceTokens>().await?);
+        }
+
+        match response.json::<TokenErrorBody>().await {
+            Ok(TokenErrorBody::AuthorizationPending) => continue,
+            Ok(TokenErrorBody::SlowDown) => {
+                interval += Duration::from_secs(5);
+            }
+            Ok(TokenErrorBody::AccessDenied) => return Err(DeviceFlowError::AccessDenied),
+            Ok(TokenErrorBody::ExpiredToken) => return Err(DeviceFlowError::ExpiredToken),
+            Ok(TokenErrorBody::Other) | Err(_) => {
+                return Err(DeviceFlowError::Provider(
+                    "token endpoint returned an unrecognized error".into(),
+                ))
+            }
+        }
+    }
+}
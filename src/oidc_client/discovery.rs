@@ -0,0 +1,112 @@
+//! Bootstraps a [`Validator`] and the endpoints needed by the other OIDC
+//! flows in this crate from nothing more than an issuer URL, via the
+//! provider metadata document at `<issuer>/.well-known/openid-configuration`
+//! (OpenID Connect Discovery 1.0).
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+use oidc_jwt_validator::{Validator, ValidatorConfig};
+use serde::Deserialize;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum DiscoveryError {
+    #[error("failed to fetch discovery document from {url}: {source}")]
+    Fetch {
+        url: String,
+        source: reqwest::Error,
+    },
+    #[error("discovery document at {url} declares issuer \"{declared}\", expected \"{expected}\"")]
+    IssuerMismatch {
+        url: String,
+        declared: String,
+        expected: String,
+    },
+    #[error("failed to build validator from discovered metadata: {0}")]
+    Validator(#[from] oidc_jwt_validator::Error),
+}
+
+/// The subset of OpenID Provider Metadata (OIDC Discovery §3) this crate
+/// needs, both to build the [`Validator`] and to drive other flows (the
+/// device grant and introspection modules).
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ProviderMetadata {
+    pub issuer: String,
+    pub jwks_uri: String,
+    pub token_endpoint: String,
+    #[serde(default)]
+    pub device_authorization_endpoint: Option<String>,
+    #[serde(default)]
+    pub introspection_endpoint: Option<String>,
+    #[serde(default)]
+    pub id_token_signing_alg_values_supported: Vec<String>,
+    #[serde(default)]
+    pub scopes_supported: Vec<String>,
+}
+
+/// Process-wide cache of already-discovered providers, keyed by the issuer
+/// URL exactly as requested.
+fn cache() -> &'static RwLock<HashMap<String, (ProviderMetadata, Arc<Validator>)>> {
+    static CACHE: OnceLock<RwLock<HashMap<String, (ProviderMetadata, Arc<Validator>)>>> = OnceLock::new();
+    CACHE.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Fetches, validates, and caches provider metadata for `issuer`, then
+/// builds a [`Validator`] from it. A subsequent call for the same issuer
+/// returns the cached metadata/validator without hitting the network again.
+///
+/// The discovery document's own `issuer` field is checked against the
+/// requested URL (OIDC Discovery §4.3) so a misconfigured or spoofed
+/// document cannot silently point the crate at the wrong JWKS.
+pub(crate) async fn discover(
+    http: &reqwest::Client,
+    issuer: &str,
+) -> Result<(ProviderMetadata, Arc<Validator>), DiscoveryError> {
+    if let Some(cached) = cache().read().expect("discovery cache poisoned").get(issuer) {
+        return Ok(cached.clone());
+    }
+
+    // A terminating `/` is dropped only to build the well-known URL; the
+    // issuer check below compares against `issuer` as requested.
+    let well_known = format!("{}/.well-known/openid-configuration", issuer.trim_end_matches('/'));
+
+    let metadata = http
+        .get(&well_known)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|source| DiscoveryError::Fetch {
+            url: well_known.clone(),
+            source,
+        })?
+        .json::<ProviderMetadata>()
+        .await
+        .map_err(|source| DiscoveryError::Fetch {
+            url: well_known.clone(),
+            source,
+        })?;
+
+    if metadata.issuer != issuer {
+        return Err(DiscoveryError::IssuerMismatch {
+            url: well_known,
+            declared: metadata.issuer,
+            expected: issuer.to_string(),
+        });
+    }
+
+    let validator = Validator::new(
+        &metadata.jwks_uri,
+        ValidatorConfig::default().issuer(&[metadata.issuer.clone()]),
+    )
+    .await?;
+    let validator = Arc::new(validator);
+
+    cache()
+        .write()
+        .expect("discovery cache poisoned")
+        .insert(issuer.to_string(), (metadata.clone(), validator.clone()));
+
+    Ok((metadata, validator))
+}
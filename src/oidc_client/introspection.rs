@@ -0,0 +1,177 @@
+//! RFC 7662 token introspection, for IdPs that hand out opaque access tokens
+//! that cannot be parsed as a self-contained JWT by `oidc_jwt_validator`.
+
+use std::{
+    collections::HashMap,
+    sync::RwLock,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum IntrospectionError {
+    #[error("request to introspection endpoint failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("token is not active")]
+    Inactive,
+    #[error("introspected token has issuer \"{0}\", expected \"{1}\"")]
+    WrongIssuer(String, String),
+    #[error("introspected token is not valid for audience \"{0}\"")]
+    WrongAudience(String),
+    #[error("introspected token already expired at {0:?}")]
+    Expired(SystemTime),
+    #[error("failed to deserialize introspection response into claims: {0}")]
+    Deserialize(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Deserialize)]
+struct IntrospectionResponse {
+    active: bool,
+    #[serde(flatten)]
+    claims: Value,
+}
+
+/// A positive introspection result, cached until its `exp`.
+#[derive(Debug, Clone)]
+struct CachedResult {
+    claims: Value,
+    expires_at: SystemTime,
+}
+
+/// Validates opaque access tokens against an IdP's introspection endpoint
+/// (RFC 7662) in place of JWT validation: requires `active: true`, rejects a
+/// response whose `exp` is already in the past, and checks `iss`/`aud`
+/// against the configured expectations. Positive results are cached until
+/// the token's own `exp`, with the cache swept of expired entries on every
+/// fetch so it can't grow without bound, to avoid hammering the IdP on every
+/// request. Scope/group requirements are not checked here; use
+/// `AuthorizedClaims<T, P>` for that, same as with the JWT backend — it
+/// checks them against the full `introspect::<Value>` response, not a
+/// narrowed claims struct, so nothing declared by this endpoint is lost
+/// before the check runs.
+pub(crate) struct IntrospectionClient {
+    http: reqwest::Client,
+    introspection_endpoint: String,
+    client_id: String,
+    client_secret: String,
+    expected_issuer: String,
+    expected_audience: Option<String>,
+    cache: RwLock<HashMap<String, CachedResult>>,
+}
+
+impl IntrospectionClient {
+    pub(crate) fn new(
+        http: reqwest::Client,
+        introspection_endpoint: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+        expected_issuer: impl Into<String>,
+        expected_audience: Option<String>,
+    ) -> Self {
+        Self {
+            http,
+            introspection_endpoint: introspection_endpoint.into(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            expected_issuer: expected_issuer.into(),
+            expected_audience,
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Validates `token` (active, unexpired, correct issuer/audience) and
+    /// deserializes the introspection response's claims into `T`, exactly
+    /// like the JWT validation path does.
+    pub(crate) async fn introspect<T>(&self, token: &str) -> Result<T, IntrospectionError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        let claims = match self.cached(token) {
+            Some(claims) => claims,
+            None => self.fetch_and_cache(token).await?,
+        };
+
+        Ok(serde_json::from_value(claims)?)
+    }
+
+    fn cached(&self, token: &str) -> Option<Value> {
+        {
+            let cache = self.cache.read().expect("introspection cache poisoned");
+            match cache.get(token) {
+                Some(entry) if entry.expires_at > SystemTime::now() => return Some(entry.claims.clone()),
+                Some(_) | None => {}
+            }
+        }
+        // Entry is either absent or expired; in the expired case, drop it so
+        // a token that's never looked up again doesn't linger forever.
+        self.cache.write().expect("introspection cache poisoned").remove(token);
+        None
+    }
+
+    async fn fetch_and_cache(&self, token: &str) -> Result<Value, IntrospectionError> {
+        let response: IntrospectionResponse = self
+            .http
+            .post(&self.introspection_endpoint)
+            .basic_auth(&self.client_id, Some(&self.client_secret))
+            .form(&[("token", token)])
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        if !response.active {
+            return Err(IntrospectionError::Inactive);
+        }
+
+        if let Some(exp) = response.claims.get("exp").and_then(Value::as_u64) {
+            let expires_at = UNIX_EPOCH + Duration::from_secs(exp);
+            if expires_at <= SystemTime::now() {
+                return Err(IntrospectionError::Expired(expires_at));
+            }
+        }
+
+        if let Some(iss) = response.claims.get("iss").and_then(Value::as_str) {
+            if iss != self.expected_issuer {
+                return Err(IntrospectionError::WrongIssuer(
+                    iss.to_string(),
+                    self.expected_issuer.clone(),
+                ));
+            }
+        }
+
+        if let Some(expected_aud) = &self.expected_audience {
+            let matches = match response.claims.get("aud") {
+                Some(Value::String(s)) => s == expected_aud,
+                Some(Value::Array(vals)) => vals.iter().any(|v| v.as_str() == Some(expected_aud)),
+                _ => false,
+            };
+            if !matches {
+                return Err(IntrospectionError::WrongAudience(expected_aud.clone()));
+            }
+        }
+
+        let expires_at = response
+            .claims
+            .get("exp")
+            .and_then(Value::as_u64)
+            .map(|exp| UNIX_EPOCH + Duration::from_secs(exp))
+            .unwrap_or_else(SystemTime::now);
+
+        let mut cache = self.cache.write().expect("introspection cache poisoned");
+        // Sweep other tokens' expired entries here too, so a token that is
+        // introspected exactly once doesn't linger in the cache forever.
+        let now = SystemTime::now();
+        cache.retain(|_, entry| entry.expires_at > now);
+        cache.insert(
+            token.to_string(),
+            CachedResult {
+                claims: response.claims.clone(),
+                expires_at,
+            },
+        );
+
+        Ok(response.claims)
+    }
+}
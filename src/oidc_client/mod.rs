@@ -0,0 +1,6 @@
+//! Client-side OIDC flows used to *obtain* a token, as opposed to `web::oidc`
+//! which *validates* one already in hand.
+
+pub(crate) mod device;
+pub(crate) mod discovery;
+pub(crate) mod introspection;
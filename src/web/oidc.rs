@@ -1,4 +1,4 @@
-use std::{ops::Deref, sync::Arc};
+use std::{marker::PhantomData, ops::Deref, sync::Arc};
 
 use axum::{
     extract::{FromRef, FromRequestParts},
@@ -12,6 +12,42 @@ use axum_extra::{
 };
 use oidc_jwt_validator::Validator;
 use serde::Deserialize;
+use serde_json::Value;
+
+use crate::oidc_client::introspection::IntrospectionClient;
+
+/// Selects how a bearer token is validated: as a self-contained JWT checked
+/// locally against JWKS, or as an opaque token checked against the
+/// provider's RFC 7662 introspection endpoint. Put `Arc<AuthBackend>` in the
+/// router state so `Claims<T>`/`AuthorizedClaims<T, P>` can pick it up.
+pub(super) enum AuthBackend {
+    Jwt(Arc<Validator>),
+    Introspection(Arc<IntrospectionClient>),
+}
+
+impl AuthBackend {
+    /// Validates `token` and returns its full claim set as raw JSON, so
+    /// callers (in particular [`AuthorizedClaims`]) can check claims that
+    /// the caller's own narrowed claims struct doesn't declare.
+    pub(super) async fn authenticate_raw(&self, token: &str) -> Result<Value, String> {
+        match self {
+            AuthBackend::Jwt(validator) => validator
+                .validate::<Value>(token)
+                .await
+                .map(|data| data.claims)
+                .map_err(|err| err.to_string()),
+            AuthBackend::Introspection(client) => {
+                client.introspect::<Value>(token).await.map_err(|err| err.to_string())
+            }
+        }
+    }
+
+    /// Like [`AuthBackend::authenticate_raw`], narrowed straight into `T`.
+    pub(super) async fn authenticate<T: for<'de> Deserialize<'de>>(&self, token: &str) -> Result<T, String> {
+        let raw = self.authenticate_raw(token).await?;
+        serde_json::from_value(raw).map_err(|err| err.to_string())
+    }
+}
 
 /// Claims<T> can be used to require OpenID Connect authorization
 /// The supplied struct can be used to fetch possible relevant OpenID Connect claims
@@ -30,6 +66,7 @@ impl<T: for<'de> Deserialize<'de>> Deref for Claims<T> {
 
 pub(super) enum AuthError {
     InvalidToken(String),
+    InsufficientScope { scope: String, msg: String },
 }
 
 impl IntoResponse for AuthError {
@@ -46,34 +83,200 @@ impl IntoResponse for AuthError {
                 )
                 .body(axum::body::Body::default())
                 .expect("http invalid_token response"),
+            AuthError::InsufficientScope { scope, msg } => Response::builder()
+                .status(403)
+                .header(
+                    "WWW-Authenticate",
+                    format!(
+                        r#"Bearer realm="ssh-casign" error="insufficient_scope" error_description="{}" scope="{}""#,
+                        msg, scope
+                    ),
+                )
+                .body(axum::body::Body::default())
+                .expect("http insufficient_scope response"),
         }
     }
 }
 
+/// Extracts the caller's full claim set as raw JSON, trying the bearer
+/// header first and falling back to the browser session cookie. Shared by
+/// `Claims<T>` and `AuthorizedClaims<T, P>` so the latter can check
+/// requirements against everything the backend returned, not just whatever
+/// survived being narrowed into the caller's own `T`.
+async fn extract_raw_claims<S>(parts: &mut Parts, state: &S) -> Result<Value, AuthError>
+where
+    Arc<AuthBackend>: FromRef<S>,
+    Arc<dyn super::session::SessionStore>: FromRef<S>,
+    axum_extra::extract::cookie::Key: FromRef<S>,
+    S: Send + Sync,
+{
+    // Try a bearer token first
+    let bearer_result = parts
+        .extract::<TypedHeader<Authorization<Bearer>>>()
+        .await
+        .map_err(|err| AuthError::InvalidToken(err.to_string()));
+
+    let bearer_error = match bearer_result {
+        Ok(TypedHeader(Authorization(bearer))) => {
+            let backend = Arc::<AuthBackend>::from_ref(state);
+            match backend.authenticate_raw(bearer.token()).await {
+                Ok(claims) => return Ok(claims),
+                Err(err) => AuthError::InvalidToken(err),
+            }
+        }
+        Err(err) => err,
+    };
+
+    // No valid bearer header; fall back to the browser session cookie.
+    // Extracting the jar is infallible: it's just empty if no cookie header
+    // was sent or the key doesn't match.
+    let jar = parts
+        .extract_with_state::<axum_extra::extract::cookie::SignedCookieJar, S>(state)
+        .await
+        .expect("SignedCookieJar extraction is infallible");
+    let store = Arc::<dyn super::session::SessionStore>::from_ref(state);
+    super::session::load_from_cookie(&jar, store.as_ref())
+        .await
+        .ok_or(bearer_error)
+}
+
 #[axum::async_trait]
 impl<S, T: for<'de> Deserialize<'de>> FromRequestParts<S> for Claims<T>
 where
-    Arc<Validator>: FromRef<S>,
+    Arc<AuthBackend>: FromRef<S>,
+    Arc<dyn super::session::SessionStore>: FromRef<S>,
+    axum_extra::extract::cookie::Key: FromRef<S>,
     S: Send + Sync,
 {
     // If anything goes wrong or no session is found, redirect to the auth page
     type Rejection = AuthError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        // Extract the token from the authorization header
-        let TypedHeader(Authorization(bearer)) = parts
-            .extract::<TypedHeader<Authorization<Bearer>>>()
-            .await
-            .map_err(|err| AuthError::InvalidToken(err.to_string()))?;
-
-        // Validate token
-        let oidc_validator = Arc::<Validator>::from_ref(state);
-        let token_data = oidc_validator
-            .validate::<T>(bearer.token())
-            .await
-            .map_err(|err| AuthError::InvalidToken(err.to_string()))?;
-
-        // Return claims
-        Ok(Claims(token_data.claims))
+        let raw = extract_raw_claims(parts, state).await?;
+        let claims = serde_json::from_value(raw).map_err(|err| AuthError::InvalidToken(err.to_string()))?;
+
+        Ok(Claims(claims))
+    }
+}
+
+/// Declarative requirements checked by [`AuthorizedClaims`] before the claims
+/// are handed to a handler: a required audience, a set of OIDC scopes that
+/// must all be present in the space-separated `scope` claim, and a set of
+/// values that must all appear in a configurable group/role claim.
+///
+/// Implement this on a marker type and use it as the `P` parameter of
+/// `AuthorizedClaims<T, P>`, e.g. `AuthorizedClaims<MyClaims, RequireSshAdmins>`.
+pub(super) trait AuthPolicy {
+    /// Audience that must be present in the token's `aud` claim, if any.
+    fn required_audience() -> Option<&'static str> {
+        None
+    }
+
+    /// OIDC scopes that must all be present in the `scope` claim.
+    fn required_scopes() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Name of the claim holding the caller's groups/roles, e.g. `"groups"`.
+    fn group_claim() -> &'static str {
+        "groups"
+    }
+
+    /// Values that must all appear in [`AuthPolicy::group_claim`].
+    fn required_groups() -> &'static [&'static str] {
+        &[]
+    }
+}
+
+/// Like [`Claims<T>`], but additionally enforces an [`AuthPolicy`] before the
+/// claims are returned to the handler. A missing requirement is rejected the
+/// same way as an invalid token, except with status 403 instead of 401 and
+/// with `error="insufficient_scope"` and an RFC 6750 `scope="..."` hint in
+/// `WWW-Authenticate` instead of `invalid_token`.
+///
+/// Usage: `async fn some_axum_handler(claims: AuthorizedClaims<MyClaims, RequireSshAdmins>)`
+#[derive(Debug)]
+pub(super) struct AuthorizedClaims<T: for<'de> Deserialize<'de>, P: AuthPolicy>(T, PhantomData<P>);
+
+impl<T: for<'de> Deserialize<'de>, P: AuthPolicy> Deref for AuthorizedClaims<T, P> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+fn missing_scope(required: &[&str], present: &[&str]) -> Option<String> {
+    required
+        .iter()
+        .find(|want| !present.contains(want))
+        .map(|want| want.to_string())
+}
+
+#[axum::async_trait]
+impl<S, T, P> FromRequestParts<S> for AuthorizedClaims<T, P>
+where
+    Arc<AuthBackend>: FromRef<S>,
+    Arc<dyn super::session::SessionStore>: FromRef<S>,
+    axum_extra::extract::cookie::Key: FromRef<S>,
+    T: for<'de> Deserialize<'de>,
+    P: AuthPolicy,
+    S: Send + Sync,
+{
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let required_scope_hint = P::required_scopes().join(" ");
+
+        // Check requirements against the full claim set returned by the
+        // backend, not against a round-trip through the caller's own `T` —
+        // any claim `T` doesn't declare (typically `scope`/`groups`) would
+        // otherwise be silently dropped before it could be checked.
+        let raw = extract_raw_claims(parts, state).await?;
+
+        if let Some(aud) = P::required_audience() {
+            let matches = match raw.get("aud") {
+                Some(Value::String(s)) => s == aud,
+                Some(Value::Array(vals)) => vals.iter().any(|v| v.as_str() == Some(aud)),
+                _ => false,
+            };
+            if !matches {
+                return Err(AuthError::InsufficientScope {
+                    scope: required_scope_hint,
+                    msg: format!("token is not valid for audience \"{}\"", aud),
+                });
+            }
+        }
+
+        let granted_scopes: Vec<&str> = raw
+            .get("scope")
+            .and_then(Value::as_str)
+            .map(|s| s.split(' ').filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default();
+        if let Some(missing) = missing_scope(P::required_scopes(), &granted_scopes) {
+            return Err(AuthError::InsufficientScope {
+                scope: required_scope_hint,
+                msg: format!("missing required scope \"{}\"", missing),
+            });
+        }
+
+        let member_of: Vec<&str> = raw
+            .get(P::group_claim())
+            .and_then(Value::as_array)
+            .map(|vals| vals.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        if let Some(missing) = missing_scope(P::required_groups(), &member_of) {
+            return Err(AuthError::InsufficientScope {
+                scope: required_scope_hint,
+                msg: format!(
+                    "missing required membership in \"{}\" ({})",
+                    missing,
+                    P::group_claim()
+                ),
+            });
+        }
+
+        let claims = serde_json::from_value(raw).map_err(|err| AuthError::InvalidToken(err.to_string()))?;
+        Ok(AuthorizedClaims(claims, PhantomData))
     }
 }
\ No newline at end of file
@@ -0,0 +1,352 @@
+//! Session-cookie authentication for browser clients, so the SSH CA's web
+//! UI can drive certificate issuance without exposing raw tokens to the
+//! page.
+//!
+//! A `/login` redirect kicks off an authorization-code login at the
+//! provider; `/callback` exchanges the resulting code for tokens, validates
+//! the ID token, and stores its claims in a [`SessionStore`] keyed by a
+//! signed session cookie. `Claims<T>`/`AuthorizedClaims<T, P>` then accept
+//! that cookie as a fallback when no bearer header is present.
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{FromRef, Query, State},
+    response::Redirect,
+};
+use axum_extra::extract::cookie::{Cookie, Key, SignedCookieJar};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use super::oidc::AuthBackend;
+
+/// Name of the cookie holding the session id.
+pub(super) const SESSION_COOKIE: &str = "ssh_casign_session";
+
+/// Name of the cookie holding the pending login's CSRF `state` value.
+const OAUTH_STATE_COOKIE: &str = "ssh_casign_oauth_state";
+
+/// Name of the cookie holding the pending login's `nonce` value.
+const OAUTH_NONCE_COOKIE: &str = "ssh_casign_oauth_nonce";
+
+/// Stores the claims established by a completed authorization-code login,
+/// keyed by an opaque session id handed out as a cookie value. Modeled after
+/// `tower-sessions`' store trait, scoped down to what this crate needs.
+#[axum::async_trait]
+pub(super) trait SessionStore: Send + Sync {
+    /// Persists `claims` under a freshly generated session id and returns it.
+    async fn store(&self, claims: Value) -> String;
+
+    /// Looks up the claims for `session_id`, if the session still exists.
+    async fn load(&self, session_id: &str) -> Option<Value>;
+}
+
+/// Fallback lifetime for a session whose claims carry no `exp`. Kept short
+/// since the absence of `exp` means we have no session-derived bound at all.
+const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(300);
+
+struct StoredSession {
+    claims: Value,
+    expires_at: SystemTime,
+}
+
+/// Default in-process [`SessionStore`]. Fine for a single-instance
+/// deployment; swap in a shared store (Redis, a database) for a fleet.
+///
+/// A session never outlives the `exp` of the ID token that created it — the
+/// same rule [`crate::cert::policy`] applies to issued certificates — so a
+/// stolen cookie stops authenticating the moment the underlying OIDC session
+/// would have expired anyway. Expired entries are evicted lazily on lookup.
+#[derive(Default)]
+pub(super) struct InMemorySessionStore {
+    sessions: RwLock<HashMap<String, StoredSession>>,
+}
+
+#[axum::async_trait]
+impl SessionStore for InMemorySessionStore {
+    async fn store(&self, claims: Value) -> String {
+        let expires_at = claims
+            .get("exp")
+            .and_then(Value::as_u64)
+            .map(|exp| UNIX_EPOCH + Duration::from_secs(exp))
+            .unwrap_or_else(|| SystemTime::now() + DEFAULT_SESSION_TTL);
+
+        let session_id = Uuid::new_v4().to_string();
+        self.sessions
+            .write()
+            .await
+            .insert(session_id.clone(), StoredSession { claims, expires_at });
+        session_id
+    }
+
+    async fn load(&self, session_id: &str) -> Option<Value> {
+        {
+            let sessions = self.sessions.read().await;
+            match sessions.get(session_id) {
+                Some(session) if session.expires_at > SystemTime::now() => {
+                    return Some(session.claims.clone())
+                }
+                Some(_) => {}
+                None => return None,
+            }
+        }
+        // Session exists but has expired: evict it before reporting absence.
+        self.sessions.write().await.remove(session_id);
+        None
+    }
+}
+
+/// Reads the claims stashed by a completed login from the signed session
+/// cookie, if one is present and still valid in `store`.
+pub(super) async fn load_from_cookie(jar: &SignedCookieJar, store: &dyn SessionStore) -> Option<Value> {
+    let session_id = jar.get(SESSION_COOKIE)?;
+    store.load(session_id.value()).await
+}
+
+/// State needed to drive the authorization-code login used to populate the
+/// session store: where to send the user, and how to turn the resulting
+/// code into tokens.
+pub(super) struct LoginState {
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_uri: String,
+    pub scope: String,
+    pub cookie_key: Key,
+}
+
+impl FromRef<Arc<LoginState>> for Key {
+    fn from_ref(state: &Arc<LoginState>) -> Self {
+        state.cookie_key.clone()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct CallbackParams {
+    code: String,
+    state: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+/// `GET /login`: redirects the browser to the provider's authorization
+/// endpoint to start an authorization-code login.
+///
+/// A random `state` and `nonce` are generated and stashed in signed cookies.
+/// `/callback` refuses to complete a login whose `state` doesn't match —
+/// without this, an attacker can start their own authorization and trick a
+/// victim into visiting the resulting `/callback?code=...` URL, logging the
+/// victim's browser into the attacker's identity (RFC 6749 §10.12). `nonce`
+/// is carried through into the ID token and checked there, so a token issued
+/// for a different authorization request can't be substituted/replayed into
+/// this one (OIDC Core §3.1.2.1, §15.5.2).
+pub(super) async fn login(
+    State(login): State<Arc<LoginState>>,
+    jar: SignedCookieJar,
+) -> (SignedCookieJar, Redirect) {
+    let state = Uuid::new_v4().to_string();
+    let nonce = Uuid::new_v4().to_string();
+    let url = format!(
+        "{}?response_type=code&client_id={}&redirect_uri={}&scope={}&state={}&nonce={}",
+        login.authorization_endpoint,
+        urlencoding::encode(&login.client_id),
+        urlencoding::encode(&login.redirect_uri),
+        urlencoding::encode(&login.scope),
+        urlencoding::encode(&state),
+        urlencoding::encode(&nonce),
+    );
+    let jar = jar
+        .add(
+            Cookie::build((OAUTH_STATE_COOKIE, state))
+                .path("/")
+                .http_only(true)
+                .secure(true)
+                .build(),
+        )
+        .add(
+            Cookie::build((OAUTH_NONCE_COOKIE, nonce))
+                .path("/")
+                .http_only(true)
+                .secure(true)
+                .build(),
+        );
+    (jar, Redirect::to(&url))
+}
+
+/// `GET /callback`: checks the returned `state` and the ID token's `nonce`
+/// against the values stashed by `login`, exchanges the authorization code
+/// for tokens, validates the ID token via `backend`, and stores its claims
+/// in `store` behind a signed session cookie before redirecting the browser
+/// back to the app.
+pub(super) async fn callback(
+    State(login): State<Arc<LoginState>>,
+    State(backend): State<Arc<AuthBackend>>,
+    State(store): State<Arc<dyn SessionStore>>,
+    jar: SignedCookieJar,
+    Query(params): Query<CallbackParams>,
+) -> Result<(SignedCookieJar, Redirect), super::oidc::AuthError> {
+    let expected_state = jar
+        .get(OAUTH_STATE_COOKIE)
+        .map(|cookie| cookie.value().to_string());
+    let expected_nonce = jar
+        .get(OAUTH_NONCE_COOKIE)
+        .map(|cookie| cookie.value().to_string());
+    let jar = jar
+        .remove(Cookie::from(OAUTH_STATE_COOKIE))
+        .remove(Cookie::from(OAUTH_NONCE_COOKIE));
+    if expected_state.as_deref() != Some(params.state.as_str()) {
+        return Err(super::oidc::AuthError::InvalidToken(
+            "missing or mismatched oauth state".into(),
+        ));
+    }
+    let expected_nonce = expected_nonce.ok_or_else(|| {
+        super::oidc::AuthError::InvalidToken("missing oauth nonce cookie".into())
+    })?;
+
+    let http = reqwest::Client::new();
+    let token_response: TokenResponse = http
+        .post(&login.token_endpoint)
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", &params.code),
+            ("redirect_uri", &login.redirect_uri),
+            ("client_id", &login.client_id),
+            ("client_secret", &login.client_secret),
+        ])
+        .send()
+        .await
+        .map_err(|err| super::oidc::AuthError::InvalidToken(err.to_string()))?
+        .json()
+        .await
+        .map_err(|err| super::oidc::AuthError::InvalidToken(err.to_string()))?;
+
+    let claims: Value = backend
+        .authenticate(&token_response.id_token)
+        .await
+        .map_err(super::oidc::AuthError::InvalidToken)?;
+
+    if claims.get("nonce").and_then(Value::as_str) != Some(expected_nonce.as_str()) {
+        return Err(super::oidc::AuthError::InvalidToken(
+            "missing or mismatched id token nonce".into(),
+        ));
+    }
+
+    let session_id = store.store(claims).await;
+    let jar = jar.add(
+        Cookie::build((SESSION_COOKIE, session_id))
+            .path("/")
+            .http_only(true)
+            .secure(true)
+            .build(),
+    );
+
+    Ok((jar, Redirect::to("/")))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::{Query, State};
+    use axum_extra::extract::cookie::Key;
+
+    use super::*;
+    use crate::oidc_client::introspection::IntrospectionClient;
+
+    fn login_state(key: Key) -> Arc<LoginState> {
+        Arc::new(LoginState {
+            authorization_endpoint: "https://idp.example/authorize".to_string(),
+            token_endpoint: "https://idp.example/token".to_string(),
+            client_id: "client".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_uri: "https://ca.example/callback".to_string(),
+            scope: "openid".to_string(),
+            cookie_key: key,
+        })
+    }
+
+    fn dummy_backend() -> Arc<AuthBackend> {
+        // Never reached when `callback` rejects before exchanging the code,
+        // so the endpoint URLs here don't need to resolve to anything real.
+        Arc::new(AuthBackend::Introspection(Arc::new(IntrospectionClient::new(
+            reqwest::Client::new(),
+            "https://idp.example/introspect",
+            "client",
+            "secret",
+            "https://idp.example",
+            None,
+        ))))
+    }
+
+    fn store() -> Arc<dyn SessionStore> {
+        Arc::new(InMemorySessionStore::default())
+    }
+
+    #[tokio::test]
+    async fn callback_rejects_missing_state_cookie() {
+        let key = Key::generate();
+        let jar = SignedCookieJar::new(key.clone());
+
+        let result = callback(
+            State(login_state(key)),
+            State(dummy_backend()),
+            State(store()),
+            jar,
+            Query(CallbackParams {
+                code: "some-code".to_string(),
+                state: "attacker-supplied-state".to_string(),
+            }),
+        )
+        .await;
+
+        match result {
+            Err(super::super::oidc::AuthError::InvalidToken(msg)) => {
+                assert!(msg.contains("oauth state"))
+            }
+            Err(super::super::oidc::AuthError::InsufficientScope { .. }) => {
+                panic!("expected missing-state rejection, got InsufficientScope")
+            }
+            Ok(_) => panic!("expected missing-state rejection, got Ok"),
+        }
+    }
+
+    #[tokio::test]
+    async fn callback_rejects_mismatched_state() {
+        let key = Key::generate();
+        let jar = SignedCookieJar::new(key.clone()).add(
+            Cookie::build((OAUTH_STATE_COOKIE, "expected-state".to_string()))
+                .path("/")
+                .build(),
+        );
+
+        let result = callback(
+            State(login_state(key)),
+            State(dummy_backend()),
+            State(store()),
+            jar,
+            Query(CallbackParams {
+                code: "some-code".to_string(),
+                state: "different-state".to_string(),
+            }),
+        )
+        .await;
+
+        match result {
+            Err(super::super::oidc::AuthError::InvalidToken(msg)) => {
+                assert!(msg.contains("oauth state"))
+            }
+            Err(super::super::oidc::AuthError::InsufficientScope { .. }) => {
+                panic!("expected mismatched-state rejection, got InsufficientScope")
+            }
+            Ok(_) => panic!("expected mismatched-state rejection, got Ok"),
+        }
+    }
+}